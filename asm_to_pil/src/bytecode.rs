@@ -0,0 +1,355 @@
+/// Module providing a compact binary bytecode format for analyzed ASM programs.
+///
+/// This lets a fully analyzed program (the sequence of instructions produced by this
+/// crate, keyed by the bare instruction names that `instruction_flag` takes, with the
+/// register indices that `input_at`/`output_at` name) be lowered to a byte stream and reconstructed without re-parsing text, so
+/// compiled artifacts can be cached and distributed instead of re-analyzed from source
+/// every time.
+use std::collections::HashMap;
+
+use number::FieldElement;
+
+use crate::common::{instruction_flag, reset_instruction, return_instruction, RESET_NAME, RETURN_NAME};
+
+/// Opcode reserved for the `return` pseudo-instruction.
+const RETURN_OPCODE: u16 = 0;
+/// Opcode reserved for the `_reset` pseudo-instruction.
+const RESET_OPCODE: u16 = 1;
+/// First opcode available for user-defined instructions.
+const FIRST_USER_OPCODE: u16 = 2;
+
+/// A single analyzed instruction in its compact, post-analysis form: the instruction's
+/// flag name together with the input/output register indices produced by `input_at`
+/// and `output_at` during lowering. This is the encode/decode-friendly counterpart of
+/// a real `ast::asm_analysis::Instruction<T>`; [`EncodedInstruction::from_instruction`]
+/// builds it from a name and operand counts, and [`EncodedInstruction::to_instruction`]
+/// converts it back to the real instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedInstruction {
+    /// The bare instruction name, as passed to [`instruction_flag`] (without the
+    /// `instr_` prefix), or [`RETURN_NAME`] / [`RESET_NAME`] for the pseudo-instructions.
+    pub name: String,
+    /// Indices of the instruction's input registers, in `_input_i` order.
+    pub inputs: Vec<u32>,
+    /// Indices of the instruction's output registers, in `_output_i` order.
+    pub outputs: Vec<u32>,
+}
+
+impl EncodedInstruction {
+    /// Builds the encoded form of an instruction named `name` that binds its first
+    /// `input_count` read-only registers and its first `output_count` assignment
+    /// registers, mirroring the order `input_at`/`output_at` already assign them in.
+    pub fn from_instruction(name: &str, input_count: usize, output_count: usize) -> Self {
+        EncodedInstruction {
+            name: name.to_string(),
+            inputs: (0..input_count as u32).collect(),
+            outputs: (0..output_count as u32).collect(),
+        }
+    }
+
+    /// Reconstructs the real `ast::asm_analysis::Instruction<T>` this was derived from.
+    ///
+    /// [`RETURN_NAME`] and [`RESET_NAME`] are lowered with the same constructors the
+    /// rest of this crate uses ([`return_instruction`] and the `_reset` pc-zeroing
+    /// form), so a decoded cache entry for either pseudo-instruction is indistinguishable
+    /// from one freshly parsed from source. Any other instruction was declared
+    /// elsewhere (e.g. via `define_instruction!`), so its constraint template isn't
+    /// known to this module; `lower` is the matching generated constructor, called
+    /// with `pc_name` only. The encoded input/output indices are not passed to it.
+    pub fn to_instruction<T: FieldElement>(
+        &self,
+        pc_name: &str,
+        lower: impl FnOnce(&str) -> ast::asm_analysis::Instruction<T>,
+    ) -> ast::asm_analysis::Instruction<T> {
+        match self.name.as_str() {
+            RETURN_NAME => return_instruction(self.outputs.len(), pc_name),
+            RESET_NAME => reset_instruction(pc_name),
+            _ => lower(pc_name),
+        }
+    }
+}
+
+/// Maps bare instruction names (as passed to [`instruction_flag`], without the `instr_`
+/// prefix) to stable `u16` opcodes. Flag names such as `instr_add` are not looked up.
+///
+/// `return`/`_reset` always occupy [`RETURN_OPCODE`]/[`RESET_OPCODE`]. User-defined
+/// instructions occupy the opcodes starting at [`FIRST_USER_OPCODE`], assigned in
+/// sorted-name order over the *full, fixed instruction set of the target machine*
+/// rather than in first-use order within one program - so a registry built from that
+/// same instruction set in a different process (to decode a cached artifact) always
+/// agrees on every opcode, without having to ship the registry itself alongside the
+/// bytes.
+#[derive(Debug, Clone)]
+pub struct OpcodeRegistry {
+    name_to_opcode: HashMap<String, u16>,
+    opcode_to_name: HashMap<u16, String>,
+}
+
+impl OpcodeRegistry {
+    /// Builds a registry for `instruction_names`, the full set of instruction names
+    /// (excluding `return`/`_reset`, which are always reserved) that the target
+    /// machine supports. Opcode assignment only depends on this set, sorted, so it is
+    /// independent of program-specific encounter order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `instruction_names` includes [`RETURN_NAME`] or [`RESET_NAME`]: those
+    /// opcodes are reserved and always assigned by this constructor, so a caller-supplied
+    /// entry for either would silently overwrite them instead of erroring at the
+    /// analyzer bug that produced it.
+    pub fn for_instruction_set<'a>(instruction_names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut names: Vec<&str> = instruction_names.into_iter().collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let mut registry = OpcodeRegistry {
+            name_to_opcode: HashMap::new(),
+            opcode_to_name: HashMap::new(),
+        };
+        registry.insert(RETURN_NAME, RETURN_OPCODE);
+        registry.insert(RESET_NAME, RESET_OPCODE);
+        for (offset, name) in names.into_iter().enumerate() {
+            assert!(
+                name != RETURN_NAME && name != RESET_NAME,
+                "{name} is a reserved instruction name and cannot be part of a caller-supplied instruction set"
+            );
+            registry.insert(name, FIRST_USER_OPCODE + offset as u16);
+        }
+        registry
+    }
+
+    fn insert(&mut self, name: &str, opcode: u16) {
+        self.name_to_opcode.insert(name.to_string(), opcode);
+        self.opcode_to_name.insert(opcode, name.to_string());
+    }
+
+    /// Looks up `name`'s opcode. Returns `None` if `name` isn't part of the
+    /// instruction set this registry was built from.
+    pub fn opcode_for(&self, name: &str) -> Option<u16> {
+        self.name_to_opcode.get(name).copied()
+    }
+
+    /// Looks up the instruction name registered for `opcode`, if any.
+    pub fn name_for(&self, opcode: u16) -> Option<&str> {
+        self.opcode_to_name.get(&opcode).map(String::as_str)
+    }
+}
+
+/// An error produced while encoding or decoding a bytecode stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The opcode read from the stream has no instruction registered for it.
+    UnknownOpcode(u16),
+    /// The stream ended before an instruction's operand list was fully read.
+    TruncatedOperands,
+    /// An instruction's name is not part of the `OpcodeRegistry` used to encode it.
+    UnregisteredInstruction(String),
+}
+
+/// Serializes an analyzed program to a compact byte stream.
+///
+/// Each instruction is encoded as `[opcode: u16][n_inputs: u16][n_outputs: u16]
+/// [input indices...: u16][output indices...: u16]`, with opcodes assigned by
+/// `registry` (reserved opcodes for `return`/`_reset`, a fixed per-instruction-set
+/// opcode for everything else). The resulting halfwords are packed pairwise into
+/// big-endian `u32`s, with a trailing zero halfword appended if the total count is odd.
+pub fn to_bytes(
+    program: &[EncodedInstruction],
+    registry: &OpcodeRegistry,
+) -> Result<Vec<u8>, DecodeError> {
+    let mut halfwords = Vec::new();
+    for instruction in program {
+        let opcode = registry
+            .opcode_for(&instruction.name)
+            .ok_or_else(|| DecodeError::UnregisteredInstruction(instruction.name.clone()))?;
+        halfwords.push(opcode);
+        halfwords.push(instruction.inputs.len() as u16);
+        halfwords.push(instruction.outputs.len() as u16);
+        halfwords.extend(instruction.inputs.iter().map(|&i| i as u16));
+        halfwords.extend(instruction.outputs.iter().map(|&i| i as u16));
+    }
+
+    if halfwords.len() % 2 != 0 {
+        halfwords.push(0);
+    }
+
+    let mut bytes = Vec::with_capacity(halfwords.len() * 2);
+    for pair in halfwords.chunks_exact(2) {
+        let word = ((pair[0] as u32) << 16) | (pair[1] as u32);
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Deserializes a byte stream produced by [`to_bytes`] back into [`EncodedInstruction`]s.
+///
+/// `registry` only needs to be built from the same instruction set as the one used to
+/// encode - not to be the exact same `OpcodeRegistry` instance - since opcode
+/// assignment is deterministic given that set. Yields a [`DecodeError`] instead of
+/// panicking on an unknown opcode or a truncated operand list, so callers can reject a
+/// corrupt or incompatible cache entry cleanly; decoding stops after the first error.
+/// A lone trailing zero halfword is the padding [`to_bytes`] appends and is skipped.
+pub fn from_bytes<'a>(
+    bytes: &'a [u8],
+    registry: &'a OpcodeRegistry,
+) -> impl Iterator<Item = Result<EncodedInstruction, DecodeError>> + 'a {
+    let halfwords: Vec<u16> = bytes
+        .chunks_exact(4)
+        .flat_map(|word| {
+            let word = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            [(word >> 16) as u16, word as u16]
+        })
+        .collect();
+
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        if pos >= halfwords.len() || (pos + 1 == halfwords.len() && halfwords[pos] == 0) {
+            return None;
+        }
+
+        let read = |pos: &mut usize| -> Result<u16, DecodeError> {
+            let value = *halfwords.get(*pos).ok_or(DecodeError::TruncatedOperands)?;
+            *pos += 1;
+            Ok(value)
+        };
+
+        let result = (|| {
+            let opcode = read(&mut pos)?;
+            let name = registry
+                .name_for(opcode)
+                .ok_or(DecodeError::UnknownOpcode(opcode))?
+                .to_string();
+            let n_inputs = read(&mut pos)? as usize;
+            let n_outputs = read(&mut pos)? as usize;
+
+            let mut inputs = Vec::with_capacity(n_inputs);
+            for _ in 0..n_inputs {
+                inputs.push(read(&mut pos)? as u32);
+            }
+            let mut outputs = Vec::with_capacity(n_outputs);
+            for _ in 0..n_outputs {
+                outputs.push(read(&mut pos)? as u32);
+            }
+
+            Ok(EncodedInstruction {
+                name,
+                inputs,
+                outputs,
+            })
+        })();
+        if result.is_err() {
+            // The operand counts can no longer be trusted, so nothing after this decodes.
+            pos = halfwords.len();
+        }
+        Some(result)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_program() -> Vec<EncodedInstruction> {
+        vec![
+            EncodedInstruction::from_instruction("add", 2, 1),
+            EncodedInstruction::from_instruction(RESET_NAME, 0, 0),
+            EncodedInstruction::from_instruction(RETURN_NAME, 0, 2),
+        ]
+    }
+
+    fn sample_registry() -> OpcodeRegistry {
+        OpcodeRegistry::for_instruction_set(["add", "sub"])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = sample_program();
+        let registry = sample_registry();
+        let bytes = to_bytes(&program, &registry).unwrap();
+        let decoded: Result<Vec<_>, _> = from_bytes(&bytes, &registry).collect();
+        assert_eq!(decoded.unwrap(), program);
+    }
+
+    #[test]
+    fn test_round_trip_across_independently_built_registries() {
+        // A registry built from the same instruction set in a different process (or
+        // with its members listed in a different order) must still agree on every
+        // opcode, since a cached artifact is typically decoded elsewhere.
+        let program = sample_program();
+        let encode_registry = OpcodeRegistry::for_instruction_set(["add", "sub"]);
+        let decode_registry = OpcodeRegistry::for_instruction_set(["sub", "add"]);
+
+        let bytes = to_bytes(&program, &encode_registry).unwrap();
+        let decoded: Result<Vec<_>, _> = from_bytes(&bytes, &decode_registry).collect();
+        assert_eq!(decoded.unwrap(), program);
+    }
+
+    #[test]
+    fn test_reserved_opcodes() {
+        let registry = sample_registry();
+        assert_eq!(registry.opcode_for(RETURN_NAME), Some(RETURN_OPCODE));
+        assert_eq!(registry.opcode_for(RESET_NAME), Some(RESET_OPCODE));
+    }
+
+    #[test]
+    fn test_unregistered_instruction_errors_on_encode() {
+        let registry = OpcodeRegistry::for_instruction_set(["add"]);
+        let program = vec![EncodedInstruction::from_instruction("mul", 2, 1)];
+        assert_eq!(
+            to_bytes(&program, &registry),
+            Err(DecodeError::UnregisteredInstruction("mul".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_opcode_errors() {
+        let registry = sample_registry();
+        // Opcode 0xffff was never registered.
+        let bytes = [(0xffffu32 << 16).to_be_bytes(), 0u32.to_be_bytes()].concat();
+        let decoded: Vec<_> = from_bytes(&bytes, &registry).collect();
+        assert_eq!(decoded, vec![Err(DecodeError::UnknownOpcode(0xffff))]);
+    }
+
+    #[test]
+    fn test_truncated_operands_errors() {
+        let registry = sample_registry();
+        let opcode = registry.opcode_for("add").unwrap();
+        // Declares 2 inputs and 0 outputs but the stream ends after the first input.
+        let bytes = [(((opcode as u32) << 16) | 2).to_be_bytes(), 7u32.to_be_bytes()].concat();
+        let decoded: Vec<_> = from_bytes(&bytes, &registry).collect();
+        assert_eq!(decoded, vec![Err(DecodeError::TruncatedOperands)]);
+    }
+
+    #[test]
+    fn test_to_instruction_return_matches_return_instruction_source() {
+        // `to_instruction`'s RETURN_NAME arm delegates to `common::return_instruction`,
+        // which builds its text from `return_instruction_source` - assert on that
+        // directly, since `Instruction` has no public accessor for its source text.
+        let encoded = EncodedInstruction::from_instruction(RETURN_NAME, 0, 2);
+        assert_eq!(
+            crate::common::return_instruction_source(encoded.outputs.len(), "pc"),
+            "_output_0, _output_1 { pc' = 0 }"
+        );
+        let _ = encoded.to_instruction::<u32>("pc", |_| unreachable!("return is built in"));
+    }
+
+    #[test]
+    fn test_to_instruction_reset_matches_reset_instruction_source() {
+        let encoded = EncodedInstruction::from_instruction(RESET_NAME, 0, 0);
+        assert_eq!(crate::common::reset_instruction_source("pc"), "{ pc' = 0 }");
+        let _ = encoded.to_instruction::<u32>("pc", |_| unreachable!("reset is built in"));
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved instruction name")]
+    fn test_for_instruction_set_rejects_reserved_name() {
+        OpcodeRegistry::for_instruction_set(["add", RETURN_NAME]);
+    }
+
+    #[test]
+    fn test_instruction_flag_used_for_display_name_only() {
+        // Opcodes are keyed by the bare instruction name; `instruction_flag` is only
+        // used to derive the PIL selector name elsewhere, not the encoded bytes.
+        assert_eq!(instruction_flag("add"), "instr_add");
+    }
+}
@@ -20,6 +20,22 @@ pub fn instruction_flag(name: &str) -> String {
     format!("instr_{name}")
 }
 
+/// Generates a guarded instruction flag: the product of the instruction's ordinary
+/// selector and a boolean guard column, so that all of the instruction's constraints
+/// (register assignments, `pc'` update) become no-ops whenever the guard is false.
+///
+/// # Arguments
+///
+/// * `name` - A string slice that holds the name of the instruction.
+/// * `guard_col` - The name of the boolean column gating the instruction.
+///
+/// # Returns
+///
+/// A `String` representing the guarded selector expression `instr_<name> * <guard_col>`.
+pub fn guarded_instruction_flag(name: &str, guard_col: &str) -> String {
+    format!("{} * {guard_col}", instruction_flag(name))
+}
+
 /// Generates the names of the output assignment registers for a given count.
 ///
 /// # Arguments
@@ -73,12 +89,140 @@ pub fn return_instruction<T: FieldElement>(
     output_count: usize,
     pc_name: &str,
 ) -> ast::asm_analysis::Instruction<T> {
-    parse_instruction(&format!(
+    parse_instruction(&return_instruction_source(output_count, pc_name))
+}
+
+/// Builds the source text passed to `parse_instruction` for an unconditional return.
+///
+/// Exposed as its own function (rather than inlined into [`return_instruction`]) so
+/// tests and other crate modules can check the generated constraint text - and compare
+/// it against the guarded, tail-call, or reset forms - without depending on
+/// `Instruction`'s internals. The other `*_source` helpers in this crate follow the
+/// same pattern.
+pub(crate) fn return_instruction_source(output_count: usize, pc_name: &str) -> String {
+    format!(
         "{} {{ {pc_name}' = 0 }}",
         output_registers(output_count).join(", ")
+    )
+}
+
+/// Generates the `_reset` instruction for a given program counter name.
+///
+/// # Arguments
+///
+/// * `pc_name` - The name of the program counter.
+///
+/// # Returns
+///
+/// An `Instruction` instance representing the reset instruction.
+pub fn reset_instruction<T: FieldElement>(pc_name: &str) -> ast::asm_analysis::Instruction<T> {
+    parse_instruction(&reset_instruction_source(pc_name))
+}
+
+/// Builds the source text passed to `parse_instruction` for [`reset_instruction`]. See
+/// [`return_instruction_source`] for why this is exposed separately.
+pub(crate) fn reset_instruction_source(pc_name: &str) -> String {
+    format!("{{ {pc_name}' = 0 }}")
+}
+
+/// Generates a return instruction, optionally guarded by a boolean condition column.
+///
+/// This lowers early-return patterns (`if cond return x`) to a single predicated
+/// `return` instead of a branch plus jump: the guard column is constrained boolean,
+/// and `pc'` is only forced to `0` while it is true, via `pc' * guard = 0` (true for
+/// any `pc'` when `guard` is `0`, and only satisfiable by `pc' = 0` when `guard` is `1`).
+///
+/// When `guard` is `None`, the output is byte-identical to [`return_instruction`].
+///
+/// # Arguments
+///
+/// * `output_count` - The number of output registers.
+/// * `pc_name` - The name of the program counter.
+/// * `guard` - An optional boolean guard column; `None` for an unconditional return.
+///
+/// # Returns
+///
+/// An `Instruction` instance representing the (possibly guarded) return instruction.
+pub fn return_instruction_guarded<T: FieldElement>(
+    output_count: usize,
+    pc_name: &str,
+    guard: Option<&str>,
+) -> ast::asm_analysis::Instruction<T> {
+    parse_instruction(&return_instruction_guarded_source(
+        output_count,
+        pc_name,
+        guard,
     ))
 }
 
+/// Builds the source text passed to `parse_instruction` for a (possibly guarded)
+/// return. See [`return_instruction_source`] for why this is exposed separately.
+fn return_instruction_guarded_source(
+    output_count: usize,
+    pc_name: &str,
+    guard: Option<&str>,
+) -> String {
+    match guard {
+        None => return_instruction_source(output_count, pc_name),
+        Some(guard_col) => format!(
+            "{} {{ {pc_name}' * {guard_col} = 0, {guard_col} * (1 - {guard_col}) = 0 }}",
+            output_registers(output_count).join(", ")
+        ),
+    }
+}
+
+/// Generates a tail-call instruction that jumps directly into the callee's entry point
+/// instead of going through the normal call+return sequence.
+///
+/// Unlike a regular call, this does not push a new return address onto the call stack:
+/// it only sets `pc'` to `callee_pc_name`, leaving the existing return-address stack
+/// pointer untouched, so that the callee's eventual `return` restores the *original*
+/// caller's address. The `_output_0.._output_{n-1}` registers are threaded through
+/// unchanged, since the callee will bind them itself when it returns.
+///
+/// # Arguments
+///
+/// * `callee_pc_name` - The entry-point label of the tail-called function.
+/// * `caller_output_count` - The number of outputs declared by the current function.
+/// * `callee_output_count` - The number of outputs declared by the tail-called function.
+/// * `pc_name` - The name of the program counter.
+///
+/// # Returns
+///
+/// `Some(Instruction)` implementing the tail call if `caller_output_count` equals
+/// `callee_output_count`. If the arities differ, returns `None`: reusing the caller's
+/// return-address slot would make the eventual `return` restore the wrong number of
+/// outputs, so the caller must fall back to the normal call+return sequence instead.
+pub fn tail_call_instruction<T: FieldElement>(
+    callee_pc_name: &str,
+    caller_output_count: usize,
+    callee_output_count: usize,
+    pc_name: &str,
+) -> Option<ast::asm_analysis::Instruction<T>> {
+    if caller_output_count != callee_output_count {
+        return None;
+    }
+
+    Some(parse_instruction(&tail_call_instruction_source(
+        callee_pc_name,
+        caller_output_count,
+        pc_name,
+    )))
+}
+
+/// Builds the source text passed to `parse_instruction` for a tail call. See
+/// [`return_instruction_source`] for why this is exposed separately.
+fn tail_call_instruction_source(
+    callee_pc_name: &str,
+    output_count: usize,
+    pc_name: &str,
+) -> String {
+    format!(
+        "{} {{ {pc_name}' = {callee_pc_name} }}",
+        output_registers(output_count).join(", ")
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +256,68 @@ mod tests {
         let result = return_instruction::<u32>(2, "pc");
         // Validate result with expected value (depends on implementation details of parse_instruction and Instruction).
     }
+
+    #[test]
+    fn test_reset_instruction_source() {
+        assert_eq!(reset_instruction_source("pc"), "{ pc' = 0 }");
+    }
+
+    #[test]
+    fn test_tail_call_instruction_matching_arity() {
+        // This test is hypothetical and assumes the existence of the parse_instruction function and Instruction type.
+        let result = tail_call_instruction::<u32>("g_entry", 2, 2, "pc");
+        assert!(result.is_some());
+    }
+
+    #[test]
+    fn test_tail_call_instruction_source_includes_outputs() {
+        assert_eq!(
+            tail_call_instruction_source("g_entry", 2, "pc"),
+            "_output_0, _output_1 { pc' = g_entry }"
+        );
+    }
+
+    #[test]
+    fn test_tail_call_instruction_source_no_outputs() {
+        assert_eq!(
+            tail_call_instruction_source("g_entry", 0, "pc"),
+            " { pc' = g_entry }"
+        );
+    }
+
+    #[test]
+    fn test_tail_call_instruction_mismatched_arity_falls_back() {
+        let result = tail_call_instruction::<u32>("g_entry", 2, 1, "pc");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_guarded_instruction_flag() {
+        assert_eq!(
+            guarded_instruction_flag("test", "cond"),
+            "instr_test * cond"
+        );
+    }
+
+    #[test]
+    fn test_return_instruction_guarded_absent_matches_unguarded() {
+        // Absent a guard, the generated source must be byte-identical to
+        // `return_instruction`'s, since `return_instruction_guarded` delegates to the
+        // same source builder in that case.
+        assert_eq!(
+            return_instruction_guarded_source(2, "pc", None),
+            return_instruction_source(2, "pc"),
+        );
+    }
+
+    #[test]
+    fn test_return_instruction_guarded_present() {
+        let source = return_instruction_guarded_source(1, "pc", Some("cond"));
+        assert_eq!(
+            source,
+            "_output_0 { pc' * cond = 0, cond * (1 - cond) = 0 }"
+        );
+        assert!(source.contains("pc' * cond = 0"));
+        assert!(source.contains("cond * (1 - cond) = 0"));
+    }
 }
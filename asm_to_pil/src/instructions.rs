@@ -0,0 +1,205 @@
+/// Module for declaring built-in instructions and their PIL constraint templates.
+///
+/// Instruction wiring used to be assembled by hand from the string helpers in
+/// [`crate::common`] (`instruction_flag`, `input_at`, `output_at`) plus a `format!`
+/// call into [`crate::utils::parse_instruction`]. [`define_instruction!`] centralizes
+/// that boilerplate: given a name, an input/output signature, and a constraint
+/// template, it generates the flag name, binds `_input_i`/`_output_i` in order, and
+/// produces the resulting `ast::asm_analysis::Instruction<T>` in one declaration -
+/// the way an instruction-analyzer crate centralizes each opcode's inputs, outputs and
+/// clobbers in one place.
+use std::collections::HashMap;
+
+/// Generates a function implementing a built-in instruction from its name, its
+/// input/output signature, and a constraint template.
+///
+/// The template is a closure body of the form `|pc_name| <expr-producing-a-String>`;
+/// the declared `inputs`/`outputs` identifiers are bound in scope as the `_input_i`/
+/// `_output_i` register names before the template runs, in declaration order, so a
+/// template that references an operand outside its declared arity (a typo, or one
+/// signature evolving without the other) is a compile error rather than a runtime
+/// surprise.
+///
+/// # Example
+///
+/// ```ignore
+/// define_instruction! {
+///     fn reset_instruction, source_fn: reset_instruction_source, flag_fn: reset_instruction_flag,
+///     instr_name: "_reset",
+///     inputs: [],
+///     outputs: [],
+///     constraints: |pc_name| format!("{{ {pc_name}' = 0 }}"),
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_instruction {
+    (
+        fn $fn_name:ident, source_fn: $source_fn_name:ident, flag_fn: $flag_fn_name:ident,
+        instr_name: $instr_name:expr,
+        inputs: [$($input:ident),* $(,)?],
+        outputs: [$($output:ident),* $(,)?],
+        constraints: |$pc:ident| $body:expr $(,)?
+    ) => {
+        /// Generates the built-in instruction declared by this `define_instruction!` call.
+        pub fn $fn_name<T: ::number::FieldElement>(
+            $pc: &str,
+        ) -> ast::asm_analysis::Instruction<T> {
+            $crate::utils::parse_instruction(&$source_fn_name($pc))
+        }
+
+        /// Builds the source text passed to `parse_instruction` for this instruction. See
+        /// [`crate::common::return_instruction_source`] for why this is exposed separately.
+        #[allow(unused_mut, unused_variables, unused_assignments)]
+        pub fn $source_fn_name($pc: &str) -> String {
+            let (
+                $($input,)*
+                $($output,)*
+            ) = {
+                let mut __next_input = 0usize;
+                $(
+                    let $input = $crate::common::input_at(__next_input);
+                    __next_input += 1;
+                )*
+                let mut __next_output = 0usize;
+                $(
+                    let $output = $crate::common::output_at(__next_output);
+                    __next_output += 1;
+                )*
+                ($($input,)* $($output,)*)
+            };
+
+            $body
+        }
+
+        /// The PIL selector name for this instruction, i.e. `instruction_flag(instr_name)`.
+        #[allow(dead_code)]
+        pub fn $flag_fn_name() -> String {
+            $crate::common::instruction_flag($instr_name)
+        }
+    };
+}
+
+/// An equivalent constraint expansion to substitute for a [`define_instruction!`]-
+/// declared instruction when the underlying backend does not implement it natively.
+pub type FallbackLowering<T> = Box<dyn Fn(&str) -> ast::asm_analysis::Instruction<T>>;
+
+/// Registers fallback lowerings for built-in instructions, keyed by instruction name,
+/// so a backend that lacks a given opcode can fall back to an equivalent expansion in
+/// terms of constraints it does support, mirroring how assemblers work around a target
+/// missing an instruction.
+pub struct FallbackRegistry<T> {
+    fallbacks: HashMap<String, FallbackLowering<T>>,
+}
+
+impl<T> Default for FallbackRegistry<T> {
+    fn default() -> Self {
+        Self {
+            fallbacks: HashMap::new(),
+        }
+    }
+}
+
+impl<T> FallbackRegistry<T> {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `fallback` as the constraint expansion to use for `instr_name` on
+    /// backends that don't implement it natively.
+    pub fn register(&mut self, instr_name: &str, fallback: FallbackLowering<T>) {
+        self.fallbacks.insert(instr_name.to_string(), fallback);
+    }
+
+    /// Lowers `instr_name` for `pc_name`, using `native` if `backend_supports_natively`
+    /// is `true`, otherwise the registered fallback expansion. Falls back to `native`
+    /// unchanged if no fallback was registered for `instr_name`.
+    pub fn lower(
+        &self,
+        instr_name: &str,
+        pc_name: &str,
+        backend_supports_natively: bool,
+        native: impl FnOnce(&str) -> ast::asm_analysis::Instruction<T>,
+    ) -> ast::asm_analysis::Instruction<T> {
+        if !backend_supports_natively {
+            if let Some(fallback) = self.fallbacks.get(instr_name) {
+                return fallback(pc_name);
+            }
+        }
+        native(pc_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::RESET_NAME;
+
+    define_instruction! {
+        fn reset_instruction, source_fn: reset_instruction_source, flag_fn: reset_instruction_flag,
+        instr_name: RESET_NAME,
+        inputs: [],
+        outputs: [],
+        constraints: |pc_name| format!("{{ {pc_name}' = 0 }}"),
+    }
+
+    #[test]
+    fn test_reset_instruction_flag() {
+        assert_eq!(reset_instruction_flag(), "instr__reset");
+    }
+
+    #[test]
+    fn test_reset_instruction_builds() {
+        // A macro regression that drops a binding or mis-orders `_input_i`/`_output_i`
+        // would otherwise pass this suite silently, since `Instruction` isn't
+        // comparable here - so assert on the generated source text instead.
+        assert_eq!(reset_instruction_source("pc"), "{ pc' = 0 }");
+    }
+
+    define_instruction! {
+        fn add_instruction, source_fn: add_instruction_source, flag_fn: add_instruction_flag,
+        instr_name: "add",
+        inputs: [a, b],
+        outputs: [sum],
+        constraints: |pc_name| format!("{sum} {{ {sum} = {a} + {b}, {pc_name}' = {pc_name} + 1 }}"),
+    }
+
+    #[test]
+    fn test_add_instruction_binds_operands_in_order() {
+        assert_eq!(
+            add_instruction_source("pc"),
+            "_output_0 { _output_0 = _input_0 + _input_1, pc' = pc + 1 }"
+        );
+    }
+
+    #[test]
+    fn test_fallback_registry_uses_native_when_supported() {
+        let mut registry = FallbackRegistry::<u32>::new();
+        registry.register(
+            "reset",
+            Box::new(|_: &str| -> ast::asm_analysis::Instruction<u32> {
+                unreachable!("fallback used although the backend supports reset")
+            }),
+        );
+
+        // Whether or not a fallback is registered, a backend that supports the
+        // instruction natively always gets the native lowering.
+        let _ = registry.lower("reset", "pc", true, reset_instruction::<u32>);
+    }
+
+    #[test]
+    fn test_fallback_registry_uses_fallback_when_unsupported() {
+        let mut registry = FallbackRegistry::<u32>::new();
+        registry.register("reset", Box::new(|pc_name| reset_instruction::<u32>(pc_name)));
+
+        let _ = registry.lower("reset", "pc", false, |_| {
+            unreachable!("native lowering used although a fallback is registered")
+        });
+    }
+
+    #[test]
+    fn test_fallback_registry_defaults_to_native_when_unregistered() {
+        let registry = FallbackRegistry::<u32>::new();
+        let _ = registry.lower("reset", "pc", false, reset_instruction::<u32>);
+    }
+}